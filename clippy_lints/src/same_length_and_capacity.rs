@@ -1,16 +1,22 @@
-use clippy_utils::diagnostics::span_lint_and_help;
+use clippy_utils::consts::{Constant, constant};
+use clippy_utils::diagnostics::{span_lint_and_help, span_lint_and_then};
 use clippy_utils::ty::{is_type_diagnostic_item, is_type_lang_item};
 use clippy_utils::{SpanlessEq, sym};
-use rustc_hir::{Expr, ExprKind, LangItem, QPath};
+use rustc_errors::Applicability;
+use rustc_hir::def::Res;
+use rustc_hir::{Expr, ExprKind, LangItem, Node, Pat, PatKind, QPath, StmtKind};
 use rustc_lint::{LateContext, LateLintPass};
+use rustc_middle::ty::Ty;
 use rustc_session::declare_lint_pass;
+use rustc_span::Symbol;
 use rustc_span::symbol::sym as rustc_sym;
 
 declare_clippy_lint! {
     /// ### What it does
     ///
-    /// Checks for usages of Vec::from_raw_parts and String::from_raw_parts
-    /// where the same expression is used for the length and the capacity.
+    /// Checks for usages of `Vec::from_raw_parts`, `Vec::from_raw_parts_in` and
+    /// `String::from_raw_parts` where the same expression is used for the length and the
+    /// capacity.
     ///
     /// ### Why is this bad?
     ///
@@ -21,6 +27,13 @@ declare_clippy_lint! {
     /// e.g. Box::from(some_vec), which shrinks the capacity to match
     /// the length.
     ///
+    /// ### Known problems
+    ///
+    /// Reconstructing a boxed slice via `Box::from_raw(slice::from_raw_parts(...))` is not
+    /// covered by this lint or its `from_raw_parts_capacity_too_small` /
+    /// `from_raw_parts_swapped_len_cap` siblings: a boxed slice has no capacity distinct from its
+    /// length, so there is nothing to compare against.
+    ///
     /// ### Example
     ///
     /// ```no_run
@@ -46,38 +59,281 @@ declare_clippy_lint! {
     pedantic,
     "`from_raw_parts` with same length and capacity"
 }
-declare_lint_pass!(SameLengthAndCapacity => [SAME_LENGTH_AND_CAPACITY]);
+
+declare_clippy_lint! {
+    /// ### What it does
+    ///
+    /// Checks for usages of `Vec::from_raw_parts`, `Vec::from_raw_parts_in` and
+    /// `String::from_raw_parts` where the length and capacity are different, statically known
+    /// constants, but the capacity is smaller than the length.
+    ///
+    /// ### Why is this bad?
+    ///
+    /// A `Vec` (or `String`) can never have a capacity smaller than its length: reconstructing
+    /// one this way is immediately unsound, regardless of what is later done with it.
+    ///
+    /// ### Example
+    ///
+    /// ```no_run
+    /// let original: Vec<i32> = Vec::with_capacity(8);
+    /// let (ptr, ..) = original.into_raw_parts();
+    ///
+    /// let reconstructed = unsafe { Vec::from_raw_parts(ptr, 8, 4) };
+    /// ```
+    ///
+    /// Use instead:
+    ///
+    /// ```no_run
+    /// let original: Vec<i32> = Vec::with_capacity(8);
+    /// let (ptr, ..) = original.into_raw_parts();
+    ///
+    /// let reconstructed = unsafe { Vec::from_raw_parts(ptr, 8, 8) };
+    /// ```
+    #[clippy::version = "1.91.0"]
+    pub FROM_RAW_PARTS_CAPACITY_TOO_SMALL,
+    correctness,
+    "`from_raw_parts` with a capacity smaller than the length"
+}
+
+declare_clippy_lint! {
+    /// ### What it does
+    ///
+    /// Checks for usages of `Vec::from_raw_parts`, `Vec::from_raw_parts_in` and
+    /// `String::from_raw_parts` where the length and capacity bindings produced by a preceding
+    /// `into_raw_parts()` destructuring are passed back in transposed order.
+    ///
+    /// ### Why is this bad?
+    ///
+    /// `into_raw_parts()` returns `(ptr, len, cap)`; passing the `cap` binding where `len` is
+    /// expected (and vice versa) reconstructs a value with its length and capacity swapped, which
+    /// is almost certainly not what was intended.
+    ///
+    /// ### Example
+    ///
+    /// ```no_run
+    /// let original: Vec<i32> = Vec::with_capacity(8);
+    /// let (ptr, len, cap) = original.into_raw_parts();
+    ///
+    /// let reconstructed = unsafe { Vec::from_raw_parts(ptr, cap, len) };
+    /// ```
+    ///
+    /// Use instead:
+    ///
+    /// ```no_run
+    /// let original: Vec<i32> = Vec::with_capacity(8);
+    /// let (ptr, len, cap) = original.into_raw_parts();
+    ///
+    /// let reconstructed = unsafe { Vec::from_raw_parts(ptr, len, cap) };
+    /// ```
+    #[clippy::version = "1.91.0"]
+    pub FROM_RAW_PARTS_SWAPPED_LEN_CAP,
+    correctness,
+    "`from_raw_parts` with the length and capacity arguments transposed"
+}
+
+declare_lint_pass!(SameLengthAndCapacity => [
+    SAME_LENGTH_AND_CAPACITY,
+    FROM_RAW_PARTS_CAPACITY_TOO_SMALL,
+    FROM_RAW_PARTS_SWAPPED_LEN_CAP,
+]);
+
+/// Describes one `from_raw_parts`-style reconstruction function that takes a length and a
+/// capacity argument, so [`SameLengthAndCapacity`] doesn't need a separate, near-identical
+/// `if let` arm for every type that offers one.
+///
+/// `String::from_utf8_unchecked` round-trips (e.g. wrapping a `Vec::from_raw_parts` call) don't
+/// need their own entry: the inner `Vec::from_raw_parts` call is itself visited by `check_expr`
+/// and already matches the `Vec` entry below. The slice/`Box` reconstruction path is out of
+/// scope for the same reason noted in [`SAME_LENGTH_AND_CAPACITY`]'s "Known problems" section.
+struct RawPartsFn {
+    matches_self_ty: fn(&LateContext<'_>, Ty<'_>) -> bool,
+    matches_name: fn(Symbol) -> bool,
+    len_arg: usize,
+    cap_arg: usize,
+    lint_msg: &'static str,
+    help: &'static str,
+    too_small_msg: &'static str,
+    swapped_msg: &'static str,
+}
+
+static RAW_PARTS_FNS: &[RawPartsFn] = &[
+    RawPartsFn {
+        matches_self_ty: |cx, ty| is_type_diagnostic_item(cx, ty, rustc_sym::Vec),
+        matches_name: |name| name == sym::from_raw_parts,
+        len_arg: 1,
+        cap_arg: 2,
+        lint_msg: "usage of `Vec::from_raw_parts` with the same expression for length and capacity",
+        help: "if the length and capacity are the same, you most likely went through a boxed slice; consider reconstructing the `Vec` using a `Box` instead, e.g. `Box::from(slice::from_raw_parts(...)).into_vec()`",
+        too_small_msg: "usage of `Vec::from_raw_parts` with a capacity smaller than the length",
+        swapped_msg: "usage of `Vec::from_raw_parts` with the length and capacity arguments transposed",
+    },
+    RawPartsFn {
+        matches_self_ty: |cx, ty| is_type_diagnostic_item(cx, ty, rustc_sym::Vec),
+        matches_name: |name| name.as_str() == "from_raw_parts_in",
+        len_arg: 1,
+        cap_arg: 2,
+        lint_msg: "usage of `Vec::from_raw_parts_in` with the same expression for length and capacity",
+        help: "if the length and capacity are the same, you most likely went through a boxed slice; consider reconstructing the `Vec` using a `Box` instead, e.g. `Box::from(slice::from_raw_parts(...)).into_vec()`",
+        too_small_msg: "usage of `Vec::from_raw_parts_in` with a capacity smaller than the length",
+        swapped_msg: "usage of `Vec::from_raw_parts_in` with the length and capacity arguments transposed",
+    },
+    RawPartsFn {
+        matches_self_ty: |cx, ty| is_type_lang_item(cx, ty, LangItem::String),
+        matches_name: |name| name == sym::from_raw_parts,
+        len_arg: 1,
+        cap_arg: 2,
+        lint_msg: "usage of `String::from_raw_parts` with the same expression for length and capacity",
+        help: "if the length and capacity are the same, you most likely went through a boxed `str`; consider reconstructing the `String` using `String::from` instead, e.g. `String::from(str::from_utf8_unchecked(slice::from_raw_parts(...)))`",
+        too_small_msg: "usage of `String::from_raw_parts` with a capacity smaller than the length",
+        swapped_msg: "usage of `String::from_raw_parts` with the length and capacity arguments transposed",
+    },
+];
+
+/// If `expr` is a plain local variable reference, returns the name of that local.
+fn path_to_local_name(expr: &Expr<'_>) -> Option<Symbol> {
+    if let ExprKind::Path(QPath::Resolved(None, path)) = expr.kind
+        && let Res::Local(_) = path.res
+        && let [segment] = path.segments
+    {
+        Some(segment.ident.name)
+    } else {
+        None
+    }
+}
+
+/// Method names that destructure a value into `(ptr, len, cap, ..)`, together with the tuple
+/// arity they're expected to produce: plain `into_raw_parts()` returns `(ptr, len, cap)`, while
+/// the allocator-aware `into_raw_parts_with_alloc()` returns `(ptr, len, cap, alloc)`.
+const INTO_RAW_PARTS_METHODS: &[(&str, usize)] = &[("into_raw_parts", 3), ("into_raw_parts_with_alloc", 4)];
+
+/// If `pat` is a tuple pattern of exactly `arity` elements whose first three are simple bindings,
+/// returns their names as `(ptr, len, cap)`.
+fn first_three_bindings(pat: &Pat<'_>, arity: usize) -> Option<(Symbol, Symbol, Symbol)> {
+    let PatKind::Tuple(elems, ..) = pat.kind else {
+        return None;
+    };
+    if elems.len() != arity {
+        return None;
+    }
+    let [ptr_pat, len_pat, cap_pat, ..] = elems else {
+        return None;
+    };
+    let (
+        PatKind::Binding(_, _, ptr_ident, _),
+        PatKind::Binding(_, _, len_ident, _),
+        PatKind::Binding(_, _, cap_ident, _),
+    ) = (ptr_pat.kind, len_pat.kind, cap_pat.kind)
+    else {
+        return None;
+    };
+    Some((ptr_ident.name, len_ident.name, cap_ident.name))
+}
+
+/// Looks for a preceding `let (ptr, len, cap) = _.into_raw_parts();` (or
+/// `let (ptr, len, cap, alloc) = _.into_raw_parts_with_alloc();`) in the blocks enclosing `call`,
+/// and returns the names of the `len` and `cap` locals, provided `ptr_arg` is a simple reference
+/// to that destructuring's `ptr` local.
+fn find_raw_parts_bindings(cx: &LateContext<'_>, call: &Expr<'_>, ptr_arg: &Expr<'_>) -> Option<(Symbol, Symbol)> {
+    let ptr_name = path_to_local_name(ptr_arg)?;
+
+    // The call is usually the tail of an `unsafe { .. }` block with no statements of its own, so
+    // search every enclosing block of the surrounding item, innermost first.
+    cx.tcx
+        .hir_parent_iter(call.hir_id)
+        .take_while(|(_, node)| !matches!(node, Node::Item(_) | Node::ImplItem(_) | Node::TraitItem(_)))
+        .filter_map(|(_, node)| match node {
+            Node::Block(block) => Some(block),
+            _ => None,
+        })
+        .find_map(|block| {
+            // Only statements that textually precede `call` can be the destructuring it refers
+            // to; scanning the whole block would let a `let` *after* the call match by name via
+            // shadowing. Walk backwards so that, if several destructurings share the same `ptr`
+            // name, we pick the one that actually shadows at `call`'s position rather than the
+            // first one in the block.
+            block
+                .stmts
+                .iter()
+                .filter(|stmt| stmt.span.lo() < call.span.lo())
+                .rev()
+                .find_map(|stmt| {
+                    let StmtKind::Let(local) = stmt.kind else {
+                        return None;
+                    };
+                    let ExprKind::MethodCall(method, ..) = local.init?.kind else {
+                        return None;
+                    };
+                    let arity = INTO_RAW_PARTS_METHODS
+                        .iter()
+                        .find(|(name, _)| method.ident.name.as_str() == *name)?
+                        .1;
+                    let (ptr_name_here, len_name, cap_name) = first_three_bindings(local.pat, arity)?;
+                    (ptr_name_here == ptr_name).then_some((len_name, cap_name))
+                })
+        })
+}
 
 impl<'tcx> LateLintPass<'tcx> for SameLengthAndCapacity {
     fn check_expr(&mut self, cx: &LateContext<'tcx>, expr: &'tcx Expr<'_>) {
-        if let ExprKind::Call(path_expr, args) = expr.kind
-            && let ExprKind::Path(QPath::TypeRelative(ty, fn_path)) = path_expr.kind
-            && is_type_diagnostic_item(cx, cx.typeck_results().node_type(ty.hir_id), rustc_sym::Vec)
-            && fn_path.ident.name == sym::from_raw_parts
-            && SpanlessEq::new(cx).eq_expr(&args[1], &args[2])
-        {
-            span_lint_and_help(
-                cx,
-                SAME_LENGTH_AND_CAPACITY,
-                expr.span,
-                "usage of `Vec::from_raw_parts` with the same expression for length and capacity",
-                None,
-                "if the length and capacity are the same, you most likely went through a boxed slice; consider reconstructing the `Vec` using a `Box` instead, e.g. `Box::from(slice::from_raw_parts(...)).into_vec()`",
-            );
-        } else if let ExprKind::Call(path_expr, args) = expr.kind
-            && let ExprKind::Path(QPath::TypeRelative(ty, fn_path)) = path_expr.kind
-            && is_type_lang_item(cx, cx.typeck_results().node_type(ty.hir_id), LangItem::String)
-            && fn_path.ident.name == sym::from_raw_parts
-            && SpanlessEq::new(cx).eq_expr(&args[1], &args[2])
-        {
-            span_lint_and_help(
-                cx,
-                SAME_LENGTH_AND_CAPACITY,
-                expr.span,
-                "usage of `String::from_raw_parts` with the same expression for length and capacity",
-                None,
-                "if the length and capacity are the same, you most likely went through a boxed `str`; consider reconstructing the `String` using `String::from` instead, e.g. `String::from(str::from_utf8_unchecked(slice::from_raw_parts(...)))`",
-            );
+        let ExprKind::Call(path_expr, args) = expr.kind else {
+            return;
+        };
+        let ExprKind::Path(QPath::TypeRelative(ty, fn_path)) = path_expr.kind else {
+            return;
+        };
+        let self_ty = cx.typeck_results().node_type(ty.hir_id);
+
+        for raw_parts_fn in RAW_PARTS_FNS {
+            if !(raw_parts_fn.matches_name)(fn_path.ident.name) || !(raw_parts_fn.matches_self_ty)(cx, self_ty) {
+                continue;
+            }
+            let (Some(len), Some(cap)) = (args.get(raw_parts_fn.len_arg), args.get(raw_parts_fn.cap_arg)) else {
+                return;
+            };
+
+            let bindings = args.first().and_then(|ptr| find_raw_parts_bindings(cx, expr, ptr));
+
+            if SpanlessEq::new(cx).eq_expr(len, cap) {
+                span_lint_and_then(cx, SAME_LENGTH_AND_CAPACITY, expr.span, raw_parts_fn.lint_msg, |diag| {
+                    match bindings {
+                        Some((_, cap_name)) => {
+                            diag.span_suggestion(
+                                cap.span,
+                                "if this should have been the original capacity, use it instead",
+                                cap_name,
+                                Applicability::MaybeIncorrect,
+                            );
+                        },
+                        None => {
+                            diag.help(raw_parts_fn.help);
+                        },
+                    }
+                });
+            } else if let Some((len_name, cap_name)) = bindings
+                && path_to_local_name(len) == Some(cap_name)
+                && path_to_local_name(cap) == Some(len_name)
+            {
+                span_lint_and_then(cx, FROM_RAW_PARTS_SWAPPED_LEN_CAP, expr.span, raw_parts_fn.swapped_msg, |diag| {
+                    diag.multipart_suggestion(
+                        "swap the length and capacity arguments",
+                        vec![(len.span, cap_name.to_string()), (cap.span, len_name.to_string())],
+                        Applicability::MaybeIncorrect,
+                    );
+                });
+            } else if let Some(Constant::Int(len_val)) = constant(cx, cx.typeck_results(), len)
+                && let Some(Constant::Int(cap_val)) = constant(cx, cx.typeck_results(), cap)
+                && cap_val < len_val
+            {
+                span_lint_and_help(
+                    cx,
+                    FROM_RAW_PARTS_CAPACITY_TOO_SMALL,
+                    expr.span,
+                    raw_parts_fn.too_small_msg,
+                    None,
+                    "the capacity must be at least the length, or the value is immediately unsound to use",
+                );
+            }
+            return;
         }
     }
 }
@@ -1,5 +1,8 @@
+#![feature(allocator_api)]
 #![feature(vec_into_raw_parts)]
 #![warn(clippy::same_length_and_capacity)]
+#![warn(clippy::from_raw_parts_capacity_too_small)]
+#![warn(clippy::from_raw_parts_swapped_len_cap)]
 
 fn main() {
     let mut my_vec: Vec<i32> = Vec::with_capacity(20);
@@ -13,6 +16,18 @@ fn main() {
     // Don't want to lint different expressions for len and cap
     let _properly_reconstructed_vec = unsafe { Vec::from_raw_parts(ptr, len, cap) };
 
+    let mut my_vec_in: Vec<i32, std::alloc::Global> = Vec::new_in(std::alloc::Global);
+    my_vec_in.extend([1, 2, 3, 4, 5]);
+    let (ptr_in, len_in, cap_in, alloc_in) = my_vec_in.into_raw_parts_with_alloc();
+
+    // Also exercises the "suggest the original capacity binding" suggestion for the
+    // allocator-aware destructuring, not just the plain `into_raw_parts` one above.
+    let _reconstructed_vec_in = unsafe { Vec::from_raw_parts_in(ptr_in, len_in, len_in, alloc_in) };
+    //~^ same_length_and_capacity
+
+    // Don't want to lint different expressions for len and cap
+    let _properly_reconstructed_vec_in = unsafe { Vec::from_raw_parts_in(ptr_in, len_in, cap_in, alloc_in) };
+
     let my_string = String::from("hello");
     let (string_ptr, string_len, string_cap) = my_string.into_raw_parts();
 
@@ -21,4 +36,43 @@ fn main() {
 
     // Don't want to lint different expressions for len and cap
     let _properly_reconstructed_string = unsafe { String::from_raw_parts(string_ptr, string_len, string_cap) };
+
+    let _too_small_vec = unsafe { Vec::from_raw_parts(ptr, 8, 4) };
+    //~^ from_raw_parts_capacity_too_small
+
+    // Capacity is a constant at least as large as the length: fine
+    let _fine_vec = unsafe { Vec::from_raw_parts(ptr, 4, 8) };
+
+    // Length/capacity aren't both constant: we can't tell, so don't lint
+    let _unknown_vec = unsafe { Vec::from_raw_parts(ptr, len, 4) };
+
+    let _too_small_vec_in = unsafe { Vec::from_raw_parts_in(ptr_in, 8, 4, alloc_in) };
+    //~^ from_raw_parts_capacity_too_small
+
+    let _too_small_string = unsafe { String::from_raw_parts(string_ptr, 8, 4) };
+    //~^ from_raw_parts_capacity_too_small
+
+    let mut another_vec: Vec<i32> = Vec::with_capacity(20);
+    another_vec.extend([1, 2, 3, 4, 5]);
+    let (ptr2, len2, cap2) = another_vec.into_raw_parts();
+    let _swapped_vec = unsafe { Vec::from_raw_parts(ptr2, cap2, len2) };
+    //~^ from_raw_parts_swapped_len_cap
+
+    // Arguments are in the right order: fine
+    let _properly_ordered_vec = unsafe { Vec::from_raw_parts(ptr2, len2, cap2) };
+
+    let _swapped_vec_in = unsafe { Vec::from_raw_parts_in(ptr_in, cap_in, len_in, alloc_in) };
+    //~^ from_raw_parts_swapped_len_cap
+
+    let _swapped_string = unsafe { String::from_raw_parts(string_ptr, string_cap, string_len) };
+    //~^ from_raw_parts_swapped_len_cap
+}
+
+// Regression test: a destructuring that appears *after* the flagged call must not be picked up
+// just because it shadows the same binding names.
+unsafe fn later_destructure_is_not_matched(ptr: *mut u8, len: usize, _cap: usize, other: Vec<u8>) -> Vec<u8> {
+    let _first = unsafe { Vec::from_raw_parts(ptr, len, len) };
+    //~^ same_length_and_capacity
+    let (ptr, len, cap) = other.into_raw_parts();
+    unsafe { Vec::from_raw_parts(ptr, len, cap) }
 }